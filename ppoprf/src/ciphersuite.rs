@@ -0,0 +1,223 @@
+//! Ciphersuite abstraction for the PPOPRF.
+//!
+//! `Server`, `ProofDLEQ`, and friends are generic over a [`Ciphersuite`],
+//! following the same pattern used by the FROST and opaque-ke crates to
+//! let callers swap the underlying group without touching the protocol
+//! logic. [`Ristretto255`] is the suite this crate has always used; a
+//! NIST P-256 suite is available behind the `p256` feature for
+//! deployments that need FIPS-approved curves.
+
+use core::ops::{Add, Mul, Sub};
+
+use rand_core::RngCore;
+use serde::{de::DeserializeOwned, Serialize};
+use zeroize::Zeroize;
+
+/// A prime-order group together with the scalar field operations the
+/// PPOPRF needs: random/invertible scalars, a hash-to-group map, and
+/// fixed-length (de)serialization of group elements.
+pub trait Group: Clone {
+    /// Scalar field element (exponents, OPRF/tag keys). Required to be
+    /// `Zeroize` so OPRF keys and other transient secret scalars can be
+    /// wiped as soon as they are no longer needed.
+    type Scalar: Copy
+        + PartialEq
+        + Serialize
+        + DeserializeOwned
+        + Zeroize
+        + Add<Output = Self::Scalar>
+        + Sub<Output = Self::Scalar>
+        + Mul<Output = Self::Scalar>
+        + Mul<Self::Element, Output = Self::Element>;
+    /// Group element (points).
+    type Element: Copy + Add<Output = Self::Element>;
+    /// Fixed-size compressed/encoded form of a group element.
+    type CompressedElement: Copy + PartialEq + AsRef<[u8]>;
+
+    /// Length in bytes of a compressed element.
+    const ELEMENT_LEN: usize;
+
+    /// The group identity element.
+    fn identity() -> Self::Element;
+    /// The fixed base point used for public-key and proof computations.
+    fn generator() -> Self::Element;
+    /// Sample a uniformly random scalar.
+    fn random_scalar(rng: &mut impl RngCore) -> Self::Scalar;
+    /// Multiplicative inverse of a (necessarily nonzero) scalar.
+    fn invert(scalar: &Self::Scalar) -> Self::Scalar;
+    /// Reduce a wide (64-byte) buffer into a scalar, matching the
+    /// wide-reduction used to build a scalar from a Strobe digest.
+    fn scalar_from_bytes_mod_order_wide(bytes: &[u8; 64]) -> Self::Scalar;
+    /// Reduce a 32-byte puncturable-PRF tag into a scalar.
+    fn scalar_from_bytes_mod_order(bytes: [u8; 32]) -> Self::Scalar;
+    /// Hash a wide (64-byte) buffer directly onto the group.
+    fn hash_to_group(bytes: &[u8; 64]) -> Self::Element;
+    /// Encode an element in its compressed form.
+    fn compress(element: &Self::Element) -> Self::CompressedElement;
+    /// Decode a compressed element, rejecting invalid encodings.
+    fn decompress(compressed: &Self::CompressedElement) -> Option<Self::Element>;
+    /// Parse a compressed element out of a raw byte slice.
+    fn compressed_from_bytes(bytes: &[u8]) -> Option<Self::CompressedElement>;
+}
+
+/// A named ciphersuite for the PPOPRF: currently just a thin wrapper
+/// around a [`Group`], but kept as its own trait so a suite can later
+/// carry mode-specific context strings (see `Mode`) without disturbing
+/// the `Group` contract itself.
+pub trait Ciphersuite: Clone {
+    type Group: Group;
+
+    /// Name of the suite. It is not mixed into any hash: the DLEQ,
+    /// batch-challenge, hash-to-group input and finalize labels are the
+    /// same for every suite.
+    const ID: &'static str;
+}
+
+/// The original suite this crate has always used: Ristretto255 as
+/// implemented by `curve25519-dalek`.
+#[derive(Clone, Debug)]
+pub struct Ristretto255;
+
+impl Ciphersuite for Ristretto255 {
+    type Group = Ristretto255;
+
+    const ID: &'static str = "ristretto255";
+}
+
+mod ristretto255_group {
+    use super::Group;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+    use curve25519_dalek::scalar::Scalar;
+    use rand_core::RngCore;
+    use std::convert::TryInto;
+
+    impl Group for super::Ristretto255 {
+        type Scalar = Scalar;
+        type Element = RistrettoPoint;
+        type CompressedElement = CompressedRistretto;
+
+        const ELEMENT_LEN: usize = 32;
+
+        fn identity() -> Self::Element {
+            RistrettoPoint::default()
+        }
+
+        fn generator() -> Self::Element {
+            RISTRETTO_BASEPOINT_POINT
+        }
+
+        fn random_scalar(rng: &mut impl RngCore) -> Self::Scalar {
+            Scalar::random(rng)
+        }
+
+        fn invert(scalar: &Self::Scalar) -> Self::Scalar {
+            scalar.invert()
+        }
+
+        fn scalar_from_bytes_mod_order_wide(bytes: &[u8; 64]) -> Self::Scalar {
+            Scalar::from_bytes_mod_order_wide(bytes)
+        }
+
+        fn scalar_from_bytes_mod_order(bytes: [u8; 32]) -> Self::Scalar {
+            Scalar::from_bytes_mod_order(bytes)
+        }
+
+        fn hash_to_group(bytes: &[u8; 64]) -> Self::Element {
+            RistrettoPoint::from_uniform_bytes(bytes)
+        }
+
+        fn compress(element: &Self::Element) -> Self::CompressedElement {
+            element.compress()
+        }
+
+        fn decompress(compressed: &Self::CompressedElement) -> Option<Self::Element> {
+            compressed.decompress()
+        }
+
+        fn compressed_from_bytes(bytes: &[u8]) -> Option<Self::CompressedElement> {
+            let fixed: [u8; 32] = bytes.try_into().ok()?;
+            Some(CompressedRistretto(fixed))
+        }
+    }
+}
+
+/// NIST P-256, for deployments that need a FIPS-approved curve. Enabled
+/// with the `p256` feature; the PPOPRF protocol logic is unchanged,
+/// only the underlying group arithmetic differs.
+#[cfg(feature = "p256")]
+#[derive(Clone, Debug)]
+pub struct P256;
+
+#[cfg(feature = "p256")]
+impl Ciphersuite for P256 {
+    type Group = P256;
+
+    const ID: &'static str = "P256_XMD:SHA-256_SSWU_RO_";
+}
+
+#[cfg(feature = "p256")]
+mod p256_group {
+    use super::Group;
+    use p256::elliptic_curve::group::GroupEncoding;
+    use p256::elliptic_curve::hash2curve::{ExpandMsgXmd, GroupDigest};
+    use p256::elliptic_curve::ops::Invert;
+    use p256::elliptic_curve::Field;
+    use p256::{AffinePoint, ProjectivePoint, Scalar};
+    use rand_core::RngCore;
+    use sha2::Sha256;
+
+    impl Group for super::P256 {
+        type Scalar = Scalar;
+        type Element = ProjectivePoint;
+        type CompressedElement = [u8; 33];
+
+        const ELEMENT_LEN: usize = 33;
+
+        fn identity() -> Self::Element {
+            ProjectivePoint::IDENTITY
+        }
+
+        fn generator() -> Self::Element {
+            ProjectivePoint::GENERATOR
+        }
+
+        fn random_scalar(rng: &mut impl RngCore) -> Self::Scalar {
+            Scalar::random(rng)
+        }
+
+        fn invert(scalar: &Self::Scalar) -> Self::Scalar {
+            Invert::invert(scalar).unwrap()
+        }
+
+        fn scalar_from_bytes_mod_order_wide(bytes: &[u8; 64]) -> Self::Scalar {
+            Scalar::from_uniform_bytes(bytes.into())
+        }
+
+        fn scalar_from_bytes_mod_order(bytes: [u8; 32]) -> Self::Scalar {
+            let mut wide = [0u8; 64];
+            wide[..32].copy_from_slice(&bytes);
+            Scalar::from_uniform_bytes(&wide.into())
+        }
+
+        fn hash_to_group(bytes: &[u8; 64]) -> Self::Element {
+            p256::NistP256::hash_from_bytes::<ExpandMsgXmd<Sha256>>(
+                &[bytes],
+                &[b"ppoprf_p256_hash_to_group"],
+            )
+            .expect("hash-to-curve input is well-formed")
+        }
+
+        fn compress(element: &Self::Element) -> Self::CompressedElement {
+            AffinePoint::from(element).to_bytes().into()
+        }
+
+        fn decompress(compressed: &Self::CompressedElement) -> Option<Self::Element> {
+            Option::from(AffinePoint::from_bytes(compressed.into())).map(ProjectivePoint::from)
+        }
+
+        fn compressed_from_bytes(bytes: &[u8]) -> Option<Self::CompressedElement> {
+            bytes.try_into().ok()
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! A GGM-tree-based puncturable pseudorandom function (PPRF), used by
+//! [`crate::ppoprf::Server`] to derive and puncture per-metadata-tag
+//! keys.
+//!
+//! The tree is kept as the minimal forest of subtree seeds still
+//! covering every unpunctured leaf, rather than materialized in full:
+//! [`GGM::setup`] starts as a single seed covering the whole tree, and
+//! [`GGM::puncture`] replaces a covering subtree with the (up to
+//! [`TREE_DEPTH`]) sibling subtrees along the path to the punctured
+//! leaf, so the leaf itself is no longer derivable from anything
+//! retained. [`GGM::eval`] walks whichever subtree still covers a given
+//! leaf down to it.
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::ppoprf::strobe_hash;
+use crate::PPRFError;
+
+const SEED_LEN: usize = 32;
+// One tree level per bit of the `u8` metadata tag this PRF is indexed
+// by, so every possible tag is a distinct leaf.
+const TREE_DEPTH: u8 = 8;
+
+/// Operations a puncturable PRF must support: evaluate a path, and
+/// irrevocably puncture it so it (and nothing derived from it) can never
+/// be evaluated again.
+pub trait PPRF {
+    fn eval(&self, path: &[u8], out: &mut [u8; SEED_LEN]) -> Result<(), PPRFError>;
+    fn puncture(&mut self, path: &[u8]) -> Result<(), PPRFError>;
+}
+
+// A node seed covering every leaf whose path shares `prefix`'s top
+// `depth` bits -- i.e. a subtree that hasn't been punctured (in whole
+// or in part).
+#[derive(Clone, Serialize, Deserialize)]
+struct Subtree {
+    depth: u8,
+    prefix: u8,
+    seed: [u8; SEED_LEN],
+}
+impl Zeroize for Subtree {
+    fn zeroize(&mut self) {
+        self.seed.zeroize();
+    }
+}
+impl Subtree {
+    // Does this subtree's coverage include `path`?
+    fn covers(&self, path: u8) -> bool {
+        let mask = mask_for_depth(self.depth);
+        path & mask == self.prefix & mask
+    }
+}
+
+fn mask_for_depth(depth: u8) -> u8 {
+    if depth == 0 {
+        0
+    } else {
+        0xffu8 << (8 - depth)
+    }
+}
+
+/// Puncturable-PRF state for the server's metadata tags: a `Vec` of the
+/// subtrees that contain no punctured leaf.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GGM {
+    subtrees: Vec<Subtree>,
+}
+
+impl Zeroize for GGM {
+    fn zeroize(&mut self) {
+        for subtree in self.subtrees.iter_mut() {
+            subtree.zeroize();
+        }
+    }
+}
+impl Drop for GGM {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl GGM {
+    /// Set up a fresh tree from a random root seed: nothing is
+    /// punctured yet, so a single subtree covers every leaf.
+    pub fn setup() -> Self {
+        let mut csprng = rand_core_ristretto::OsRng;
+        let mut seed = [0u8; SEED_LEN];
+        rand_core::RngCore::fill_bytes(&mut csprng, &mut seed);
+        Self::from_seed(seed)
+    }
+
+    // Same as `setup`, but pinned to a caller-supplied root seed instead
+    // of one drawn from `OsRng`, so a fixed-vector test can reproduce a
+    // whole GGM tree byte-for-byte.
+    pub(crate) fn from_seed(seed: [u8; SEED_LEN]) -> Self {
+        Self {
+            subtrees: vec![Subtree {
+                depth: 0,
+                prefix: 0,
+                seed,
+            }],
+        }
+    }
+
+    // Derive a node's two children via a domain-separated PRG
+    // expansion, reusing the same Strobe-based construction this crate
+    // uses for every other pseudorandom derivation.
+    fn expand(seed: &[u8; SEED_LEN]) -> ([u8; SEED_LEN], [u8; SEED_LEN]) {
+        let mut out = [0u8; 2 * SEED_LEN];
+        strobe_hash(seed, "ppoprf_ggm_expand", &mut out);
+        let mut left = [0u8; SEED_LEN];
+        let mut right = [0u8; SEED_LEN];
+        left.copy_from_slice(&out[..SEED_LEN]);
+        right.copy_from_slice(&out[SEED_LEN..]);
+        out.zeroize();
+        (left, right)
+    }
+
+    fn find_covering(&self, path: u8) -> Option<usize> {
+        self.subtrees.iter().position(|s| s.covers(path))
+    }
+}
+
+impl PPRF for GGM {
+    fn eval(&self, path: &[u8], out: &mut [u8; SEED_LEN]) -> Result<(), PPRFError> {
+        let target = path[0];
+        let idx = self.find_covering(target).ok_or(PPRFError::NoPrefixFound)?;
+        let mut depth = self.subtrees[idx].depth;
+        let mut seed = self.subtrees[idx].seed;
+        while depth < TREE_DEPTH {
+            let (mut left, mut right) = Self::expand(&seed);
+            seed.zeroize();
+            let bit = (target >> (TREE_DEPTH - depth - 1)) & 1;
+            if bit == 0 {
+                right.zeroize();
+                seed = left;
+            } else {
+                left.zeroize();
+                seed = right;
+            }
+            depth += 1;
+        }
+        out.copy_from_slice(&seed);
+        seed.zeroize();
+        Ok(())
+    }
+
+    fn puncture(&mut self, path: &[u8]) -> Result<(), PPRFError> {
+        let target = path[0];
+        let idx = self
+            .find_covering(target)
+            .ok_or(PPRFError::NoPrefixFound)?;
+        let mut current = self.subtrees.remove(idx);
+        while current.depth < TREE_DEPTH {
+            let (left, right) = Self::expand(&current.seed);
+            current.seed.zeroize();
+            let next_depth = current.depth + 1;
+            let bit_shift = TREE_DEPTH - next_depth;
+            let bit = (target >> bit_shift) & 1;
+            let base = current.prefix & mask_for_depth(current.depth);
+            let (child_seed, sibling_seed) = if bit == 0 { (left, right) } else { (right, left) };
+            let sibling_prefix = base | ((1 - bit) << bit_shift);
+            self.subtrees.push(Subtree {
+                depth: next_depth,
+                prefix: sibling_prefix,
+                seed: sibling_seed,
+            });
+            let child_prefix = base | (bit << bit_shift);
+            current = Subtree {
+                depth: next_depth,
+                prefix: child_prefix,
+                seed: child_seed,
+            };
+        }
+        // `current` is now the punctured leaf itself: drop it (after
+        // wiping) instead of storing it back.
+        current.zeroize();
+        Ok(())
+    }
+}